@@ -1,5 +1,6 @@
 pub mod source_to_ast;
 pub mod types;
+pub mod visitor;
 
 mod constants;
 mod parser;
@@ -6,15 +6,35 @@ use super::utils::{
     unquote_escape_literal, unquote_escape_raw,
 };
 use crate::errors::CompilerError;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use pest::iterators::Pairs;
 use pest::{iterators::Pair, Parser};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-// Converts the source file (as a string) to an AST
+// Converts the source file (as a string) to an AST, bailing on the first
+// problem encountered. Prefer `to_ast_with_diagnostics` when you want every
+// problem in a source file reported in one pass instead of just the first
 pub fn to_ast(source: &str) -> Result<AlaniAst> {
+    let (ast, mut errors) = to_ast_with_diagnostics(source)?;
+
+    if let Some((error, span)) = errors.drain(..).next() {
+        return Err(error).with_context(|| format!("at byte range {}..{}", span.start, span.end));
+    }
+
+    Ok(ast)
+}
+
+// Converts the source file (as a string) to an AST, recovering from
+// semantic problems (an unrecognized symbol, a duplicate group name, an
+// inverted range, ...) instead of bailing on the first one: the offending
+// node becomes an `AlaniAstNodeKind::Error` placeholder and its `CompilerError`,
+// paired with the span of the offending node, is appended to the returned
+// list, and the walk continues over the rest of the source. A malformed
+// parse that pest itself can't tokenize at all is still a hard failure,
+// since there's no tree to recover into
+pub fn to_ast_with_diagnostics(source: &str) -> Result<(AlaniAst, Vec<(CompilerError, Span)>)> {
     if source.is_empty() {
-        return Ok(AlaniAst::Empty);
+        return Ok((AlaniAst::Empty, Vec::new()));
     }
 
     let mut pairs = AlaniParser::parse(Rule::root, source)?;
@@ -24,44 +44,406 @@ pub fn to_ast(source: &str) -> Result<AlaniAst> {
     // This hashmap is used as an environment for variables as we traverse the tokens
     let mut env: HashMap<String, AlaniAst> = HashMap::new();
 
-    pairs_to_ast(root_statements.into_inner(), &mut env)
+    // Capturing groups are numbered left-to-right across the whole tree, and
+    // named groups must be unique across the whole tree, so both are threaded
+    // through the walk alongside `env`
+    let mut next_capture_index: u32 = 0;
+    let mut named_groups: HashSet<String> = HashSet::new();
+    let mut errors: Vec<(CompilerError, Span)> = Vec::new();
+
+    let ast = pairs_to_ast(
+        root_statements.into_inner(),
+        &mut env,
+        &mut next_capture_index,
+        &mut named_groups,
+        &mut errors,
+    );
+
+    Ok((ast, errors))
+}
+
+// Converts a set of tokens into an AST. Never bails: any node that fails to
+// parse is recorded in `errors` and replaced with an `AlaniAstNodeKind::Error`
+// placeholder so its siblings still parse
+pub fn pairs_to_ast(
+    pairs: Pairs<Rule>,
+    env: &mut HashMap<String, AlaniAst>,
+    next_capture_index: &mut u32,
+    named_groups: &mut HashSet<String>,
+    errors: &mut Vec<(CompilerError, Span)>,
+) -> AlaniAst {
+    let nodes = pairs
+        .map(|pair| create_ast_node(pair, env, next_capture_index, named_groups, errors))
+        .collect();
+
+    AlaniAst::Root(nodes)
 }
 
-// Converts a set of tokens into an AST
-pub fn pairs_to_ast(pairs: Pairs<Rule>, env: &mut HashMap<String, AlaniAst>) -> Result<AlaniAst> {
-    let mut nodes = Vec::new();
+// Converts a token into an AST node, capturing its source span so a
+// `CompilerError` raised anywhere underneath can point back at the
+// offending range. Never fails outright: a `Rule`-specific parse failure is
+// pushed onto `errors` and swapped for an `AlaniAstNodeKind::Error` placeholder
+// so the walk can keep going
+fn create_ast_node(
+    pair: Pair<Rule>,
+    env: &mut HashMap<String, AlaniAst>,
+    next_capture_index: &mut u32,
+    named_groups: &mut HashSet<String>,
+    errors: &mut Vec<(CompilerError, Span)>,
+) -> AlaniAstNode {
+    let span = Span::from(pair.as_span());
 
-    // Iterate through tokens and create AST nodes
-    for pair in pairs {
-        let node = create_ast_node(pair, env)?;
-        nodes.push(node);
+    // Quantifiers compute their own span (the union of their quantity token
+    // and their expression), so they're handled before the default
+    // "span == this pair's span" case below
+    if pair.as_rule() == Rule::quantifier {
+        return parse_quantifier(pair, env, next_capture_index, named_groups, errors);
     }
 
-    Ok(AlaniAst::Root(nodes))
+    let result: Result<AlaniAstNodeKind> = match pair.as_rule() {
+        Rule::raw => Ok(AlaniAstNodeKind::Atom(unquote_escape_raw(&pair))),
+        Rule::literal => Ok(AlaniAstNodeKind::Atom(unquote_escape_literal(&pair))),
+        Rule::symbol => parse_symbol(pair),
+        Rule::range => parse_range(pair),
+        Rule::group => parse_group(pair, env, next_capture_index, named_groups, errors),
+        Rule::assertion => parse_assertion(pair, env, next_capture_index, named_groups, errors),
+        Rule::negative_char_class => parse_negative_char_class(pair),
+        Rule::variable_invocation => {
+            parse_variable_invocation(pair, env, next_capture_index, named_groups, errors)
+        }
+        Rule::variable_declaration => parse_variable_declaration(pair, env, errors),
+        Rule::EOI => Ok(AlaniAstNodeKind::Skip),
+        _ => Err(CompilerError::UnrecognizedSyntax.into()),
+    };
+
+    let node = match result {
+        Ok(node) => node,
+        Err(error) => {
+            // The node's own `span` (captured above) is recorded alongside
+            // the error so a caller can point back at the offending range
+            errors.push((as_compiler_error(error), span));
+            AlaniAstNodeKind::Error
+        }
+    };
+
+    AlaniAstNode { node, span }
 }
 
-// Converts a token into an AST node
-fn create_ast_node(pair: Pair<Rule>, env: &mut HashMap<String, AlaniAst>) -> Result<AlaniAstNode> {
-    let node = match pair.as_rule() {
-        Rule::raw => AlaniAstNode::Atom(unquote_escape_raw(&pair)),
-        Rule::literal => AlaniAstNode::Atom(unquote_escape_literal(&pair)),
-        Rule::symbol => parse_symbol(pair)?,
-        // Rule::range => range(pair)?, NOT YET IMPLEMENTED
-        Rule::quantifier => parse_quantifier(pair, env)?,
-        // Rule::group => group(pair, env)?, NOT YET IMPLEMENTED
-        // Rule::assertion => assertion(pair, env)?, NOT YET IMPLEMENTED
-        // Rule::negative_char_class => negative_char_class(&pair)?, NOT YET IMPLEMENTED
-        // Rule::variable_invocation => variable_invocation(&pair, env)?, NOT YET IMPLEMENTED
-        // Rule::variable_declaration => variable_declaration(pair, env)?, NOT YET IMPLEMENTED
-        Rule::EOI => AlaniAstNode::Skip,
-        _ => return Err(CompilerError::UnrecognizedSyntax.into()),
+// Every error raised while walking the tree is a `CompilerError`, so this
+// just unwraps whatever `anyhow` context was added along the way; anything
+// else would be a bug elsewhere in this module. Unwrapping loses that
+// context, but not the offending node's position: callers get that from the
+// span pushed alongside this error's return value, not from the anyhow
+// chain, so downcasting here is lossless as far as diagnostics are concerned
+fn as_compiler_error(error: anyhow::Error) -> CompilerError {
+    error
+        .downcast::<CompilerError>()
+        .unwrap_or(CompilerError::UnrecognizedSyntax)
+}
+
+// Converts a group token into a group AST node, assigning capture indices to
+// capturing and named groups in left-to-right order and rejecting named
+// groups whose name has already been used elsewhere in the tree
+fn parse_group(
+    pair: Pair<Rule>,
+    env: &mut HashMap<String, AlaniAst>,
+    next_capture_index: &mut u32,
+    named_groups: &mut HashSet<String>,
+    errors: &mut Vec<(CompilerError, Span)>,
+) -> Result<AlaniAstNodeKind> {
+    let mut inner = pair.into_inner().peekable();
+
+    let kind = match inner.peek().map(|modifier| modifier.as_rule()) {
+        Some(Rule::non_capturing_marker) => {
+            inner.next();
+            GroupKind::NonCapturing
+        }
+        Some(Rule::group_name) => {
+            let name = first_inner(inner.next().unwrap())?.as_str().to_owned();
+
+            if !named_groups.insert(name.clone()) {
+                return Err(CompilerError::DuplicateGroupName.into());
+            }
+
+            *next_capture_index += 1;
+            GroupKind::Named {
+                index: *next_capture_index,
+                name,
+            }
+        }
+        _ => {
+            *next_capture_index += 1;
+            GroupKind::Capturing {
+                index: *next_capture_index,
+            }
+        }
     };
 
-    Ok(node)
+    let body = inner
+        .map(|body_pair| create_ast_node(body_pair, env, next_capture_index, named_groups, errors))
+        .collect();
+
+    Ok(AlaniAstNodeKind::Group(Group { kind, body }))
+}
+
+// Converts an assertion token into a zero-width assertion AST node: the
+// text anchors (`start`/`end`) are handled in `parse_symbol`, while this
+// covers word boundaries and the lookaround variants, each of which wraps
+// its own inner statement list. Negation is a leading `NOT` token separate
+// from the kind marker that follows it, so a marker can be negated no
+// matter which kind it is
+fn parse_assertion(
+    pair: Pair<Rule>,
+    env: &mut HashMap<String, AlaniAst>,
+    next_capture_index: &mut u32,
+    named_groups: &mut HashSet<String>,
+    errors: &mut Vec<(CompilerError, Span)>,
+) -> Result<AlaniAstNodeKind> {
+    let mut inner = pair.into_inner().peekable();
+
+    let negated = match inner.peek() {
+        Some(token) if token.as_str() == NOT => {
+            inner.next();
+            true
+        }
+        _ => false,
+    };
+
+    let marker = inner.next().ok_or(CompilerError::MissingNode)?;
+    let marker_rule = marker.as_rule();
+
+    let body = inner
+        .map(|body_pair| create_ast_node(body_pair, env, next_capture_index, named_groups, errors))
+        .collect();
+
+    Ok(AlaniAstNodeKind::Assertion(assertion_kind(
+        marker_rule,
+        negated,
+        body,
+    )?))
+}
+
+// Maps an assertion's kind marker (already stripped of any leading `NOT`
+// token by `parse_assertion`) and its negation flag to an `AssertionKind`
+fn assertion_kind(
+    marker_rule: Rule,
+    negated: bool,
+    body: Vec<AlaniAstNode>,
+) -> Result<AssertionKind> {
+    match marker_rule {
+        Rule::lookahead_marker => Ok(AssertionKind::Lookahead { negated, body }),
+        Rule::lookbehind_marker => Ok(AssertionKind::Lookbehind { negated, body }),
+        Rule::word_boundary_marker if negated => Ok(AssertionKind::NotWordBoundary),
+        Rule::word_boundary_marker => Ok(AssertionKind::WordBoundary),
+        _ => Err(CompilerError::UnrecognizedSyntax.into()),
+    }
+}
+
+// Converts a single `a-z`-style range token into a one-item char class
+fn parse_range(pair: Pair<Rule>) -> Result<AlaniAstNodeKind> {
+    let item = parse_class_item(pair)?;
+
+    Ok(AlaniAstNodeKind::CharClass(CharClass {
+        items: vec![item],
+        negated: false,
+    }))
+}
+
+// Converts a negated char class token, e.g. `[^a-z0-9]`, into a CharClass
+// AST node whose items are negated as a whole
+fn parse_negative_char_class(pair: Pair<Rule>) -> Result<AlaniAstNodeKind> {
+    let items = pair
+        .into_inner()
+        .map(parse_class_item)
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(AlaniAstNodeKind::CharClass(CharClass {
+        items,
+        negated: true,
+    }))
+}
+
+// Converts a single char-class member into a `ClassItem`, either a bare
+// char or a `start-end` range (validated so `start <= end`)
+fn parse_class_item(pair: Pair<Rule>) -> Result<ClassItem> {
+    match pair.as_rule() {
+        Rule::range => {
+            let (start, end) = first_last_inner_str(pair)?;
+            char_range(to_char(start)?, to_char(end)?)
+        }
+        _ => Ok(ClassItem::Char(to_char(pair.as_str())?)),
+    }
+}
+
+// Builds a `start-end` range item, rejecting one where `start` sorts after
+// `end` (e.g. `z-a`), which would otherwise describe an empty range
+fn char_range(start: char, end: char) -> Result<ClassItem> {
+    if start > end {
+        return Err(CompilerError::InvertedCharRange.into());
+    }
+
+    Ok(ClassItem::Range { start, end })
+}
+
+// Evaluates a variable declaration's body and binds it in `env` under its
+// identifier, so later invocations can splice it back in. The name is only
+// inserted once its body has been fully evaluated, so a self-referential
+// declaration resolves as an undefined-variable error instead of recursing
+// forever, and redeclaring an existing name is rejected outright.
+//
+// A declaration is parsed against its own scratch capture-index counter and
+// named-group set rather than the tree-wide ones: the declaration itself is
+// emitted as `Skip` and may never be invoked, and an invocation may splice
+// the same body in more than once, so neither the indices nor the names
+// assigned here are final. `parse_variable_invocation` renumbers and
+// revalidates a fresh clone of the body against the real tree-wide state
+// every time it's spliced in
+fn parse_variable_declaration(
+    pair: Pair<Rule>,
+    env: &mut HashMap<String, AlaniAst>,
+    errors: &mut Vec<(CompilerError, Span)>,
+) -> Result<AlaniAstNodeKind> {
+    let mut inner = pair.into_inner();
+
+    let name = inner
+        .next()
+        .ok_or(CompilerError::MissingNode)?
+        .as_str()
+        .to_owned();
+    let body = inner.next().ok_or(CompilerError::MissingNode)?;
+
+    check_not_already_declared(env, &name)?;
+
+    let mut scratch_capture_index = 0;
+    let mut scratch_named_groups = HashSet::new();
+    let value = pairs_to_ast(
+        body.into_inner(),
+        env,
+        &mut scratch_capture_index,
+        &mut scratch_named_groups,
+        errors,
+    );
+
+    env.insert(name, value);
+
+    Ok(AlaniAstNodeKind::Skip)
+}
+
+// Rejects redeclaring a name already bound in `env`
+fn check_not_already_declared(env: &HashMap<String, AlaniAst>, name: &str) -> Result<()> {
+    if env.contains_key(name) {
+        return Err(CompilerError::VariableAlreadyDeclared.into());
+    }
+
+    Ok(())
+}
+
+// Looks up a previously declared variable and splices a fresh clone of its
+// body into this position in the node stream. The clone's groups are
+// renumbered and revalidated against the tree-wide capture index and
+// named-group set, since the body was only numbered against a scratch state
+// when the declaration was parsed (see `parse_variable_declaration`) and the
+// same body may be invoked, and thus spliced in, more than once
+fn parse_variable_invocation(
+    pair: Pair<Rule>,
+    env: &HashMap<String, AlaniAst>,
+    next_capture_index: &mut u32,
+    named_groups: &mut HashSet<String>,
+    errors: &mut Vec<(CompilerError, Span)>,
+) -> Result<AlaniAstNodeKind> {
+    let name = first_inner(pair)?.as_str().to_owned();
+
+    let mut body = resolve_variable_body(env, &name)?;
+
+    renumber_groups(&mut body, next_capture_index, named_groups, errors);
+
+    Ok(AlaniAstNodeKind::VariableInvocation(body))
+}
+
+// Looks up a variable's declared body by name, cloning it so each invocation
+// splices in its own copy
+fn resolve_variable_body(env: &HashMap<String, AlaniAst>, name: &str) -> Result<Vec<AlaniAstNode>> {
+    match env.get(name) {
+        Some(AlaniAst::Root(nodes)) => Ok(nodes.clone()),
+        Some(AlaniAst::Empty) => Ok(Vec::new()),
+        None => Err(CompilerError::UndefinedVariable.into()),
+    }
+}
+
+// Reassigns capture indices (left-to-right) and revalidates named-group
+// uniqueness for every group nested anywhere under `nodes`, recursing into
+// group bodies, assertion bodies, quantified groups, and nested variable
+// invocations alike. Used to renumber a variable's body against the
+// tree-wide state at each point it's spliced in
+fn renumber_groups(
+    nodes: &mut [AlaniAstNode],
+    next_capture_index: &mut u32,
+    named_groups: &mut HashSet<String>,
+    errors: &mut Vec<(CompilerError, Span)>,
+) {
+    for node in nodes.iter_mut() {
+        match &mut node.node {
+            AlaniAstNodeKind::Group(group) => {
+                if !renumber_group(group, next_capture_index, named_groups, errors) {
+                    errors.push((CompilerError::DuplicateGroupName, node.span));
+                    node.node = AlaniAstNodeKind::Error;
+                }
+            }
+            AlaniAstNodeKind::Assertion(AssertionKind::Lookahead { body, .. })
+            | AlaniAstNodeKind::Assertion(AssertionKind::Lookbehind { body, .. }) => {
+                renumber_groups(body, next_capture_index, named_groups, errors);
+            }
+            AlaniAstNodeKind::VariableInvocation(body) => {
+                renumber_groups(body, next_capture_index, named_groups, errors);
+            }
+            AlaniAstNodeKind::Quantifier(quantifier) => {
+                if let AlaniAstNodeKind::Group(group) = &mut quantifier.expression.node {
+                    // A duplicate name can't demote a quantified expression to
+                    // `Error` without losing the quantifier around it, so it
+                    // falls back to a non-capturing group instead
+                    if !renumber_group(group, next_capture_index, named_groups, errors) {
+                        errors.push((CompilerError::DuplicateGroupName, node.span));
+                        group.kind = GroupKind::NonCapturing;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Renumbers a single group and recurses into its body. Returns `false`
+// (leaving the group's body unrenumbered) if it's a named group whose name
+// collides with one already used elsewhere in the tree
+fn renumber_group(
+    group: &mut Group,
+    next_capture_index: &mut u32,
+    named_groups: &mut HashSet<String>,
+    errors: &mut Vec<(CompilerError, Span)>,
+) -> bool {
+    match &mut group.kind {
+        GroupKind::Capturing { index } => {
+            *next_capture_index += 1;
+            *index = *next_capture_index;
+        }
+        GroupKind::Named { index, name } => {
+            if !named_groups.insert(name.clone()) {
+                return false;
+            }
+
+            *next_capture_index += 1;
+            *index = *next_capture_index;
+        }
+        GroupKind::NonCapturing => {}
+    }
+
+    renumber_groups(&mut group.body, next_capture_index, named_groups, errors);
+
+    true
 }
 
 // Converts a symbol token into a symbol AST node
-fn parse_symbol(pair: Pair<Rule>) -> Result<AlaniAstNode> {
+fn parse_symbol(pair: Pair<Rule>) -> Result<AlaniAstNodeKind> {
     let (negated, symbol) = first_last_inner_str(pair)?;
 
     let negated = negated == NOT;
@@ -76,108 +458,307 @@ fn parse_symbol(pair: Pair<Rule>) -> Result<AlaniAstNode> {
     }
 
     let symbol_node = match symbol {
-        "space" => AlaniAstNode::Symbol(Symbol {
+        "space" => AlaniAstNodeKind::Symbol(Symbol {
             kind: SymbolKind::Space,
             negated,
         }),
-        "newline" => AlaniAstNode::Symbol(Symbol {
+        "newline" => AlaniAstNodeKind::Symbol(Symbol {
             kind: SymbolKind::Newline,
             negated,
         }),
-        "vertical" => AlaniAstNode::Symbol(Symbol {
+        "vertical" => AlaniAstNodeKind::Symbol(Symbol {
             kind: SymbolKind::Vertical,
             negated,
         }),
-        "word" => AlaniAstNode::Symbol(Symbol {
+        "word" => AlaniAstNodeKind::Symbol(Symbol {
             kind: SymbolKind::Word,
             negated,
         }),
-        "digit" => AlaniAstNode::Symbol(Symbol {
+        "digit" => AlaniAstNodeKind::Symbol(Symbol {
             kind: SymbolKind::Digit,
             negated,
         }),
-        "whitespace" => AlaniAstNode::Symbol(Symbol {
+        "whitespace" => AlaniAstNodeKind::Symbol(Symbol {
             kind: SymbolKind::Whitespace,
             negated,
         }),
-        "boundary" => AlaniAstNode::Symbol(Symbol {
+        "boundary" => AlaniAstNodeKind::Symbol(Symbol {
             kind: SymbolKind::Boundary,
             negated,
         }),
-        "alphabetic" => AlaniAstNode::Symbol(Symbol {
+        "alphabetic" => AlaniAstNodeKind::Symbol(Symbol {
             kind: SymbolKind::Alphabetic,
             negated,
         }),
-        "alphanumeric" => AlaniAstNode::Symbol(Symbol {
+        "alphanumeric" => AlaniAstNodeKind::Symbol(Symbol {
             kind: SymbolKind::Alphanumeric,
             negated,
         }),
-        "return" => AlaniAstNode::Symbol(Symbol {
+        "return" => AlaniAstNodeKind::Symbol(Symbol {
             kind: SymbolKind::Return,
             negated,
         }),
-        "tab" => AlaniAstNode::Symbol(Symbol {
+        "tab" => AlaniAstNodeKind::Symbol(Symbol {
             kind: SymbolKind::Tab,
             negated,
         }),
-        "null" => AlaniAstNode::Symbol(Symbol {
+        "null" => AlaniAstNodeKind::Symbol(Symbol {
             kind: SymbolKind::Null,
             negated,
         }),
-        "feed" => AlaniAstNode::Symbol(Symbol {
+        "feed" => AlaniAstNodeKind::Symbol(Symbol {
             kind: SymbolKind::Feed,
             negated,
         }),
-        "char" => AlaniAstNode::Symbol(Symbol {
+        "char" => AlaniAstNodeKind::Symbol(Symbol {
             kind: SymbolKind::Char,
             negated,
         }),
-        "backspace" => AlaniAstNode::Symbol(Symbol {
+        "backspace" => AlaniAstNodeKind::Symbol(Symbol {
             kind: SymbolKind::Backspace,
             negated,
         }),
 
-        // "start" => AlaniAstNode::SpecialSymbol(SpecialSymbol::Start),
-        // "end" => AlaniAstNode::SpecialSymbol(SpecialSymbol::End),
+        "start" => AlaniAstNodeKind::Assertion(AssertionKind::StartText),
+        "end" => AlaniAstNodeKind::Assertion(AssertionKind::EndText),
         _ => return Err(CompilerError::UnrecognizedSymbol.into()),
     };
 
     Ok(symbol_node)
 }
 
-// Converts a quantifier token into a quantifer AST node
+// Converts a quantifier token into a quantifer AST node. Delegates to
+// `try_parse_quantifier` and, on failure, records the error and produces an
+// `Error` placeholder instead of bailing, matching every other node kind
 fn parse_quantifier(
     pair: Pair<Rule>,
-    variables: &mut HashMap<String, AlaniAst>,
+    env: &mut HashMap<String, AlaniAst>,
+    next_capture_index: &mut u32,
+    named_groups: &mut HashSet<String>,
+    errors: &mut Vec<(CompilerError, Span)>,
+) -> AlaniAstNode {
+    let fallback_span = Span::from(pair.as_span());
+
+    match try_parse_quantifier(pair, env, next_capture_index, named_groups, errors) {
+        Ok(node) => node,
+        Err(error) => {
+            errors.push((as_compiler_error(error), fallback_span));
+            AlaniAstNode {
+                node: AlaniAstNodeKind::Error,
+                span: fallback_span,
+            }
+        }
+    }
+}
+
+// Converts a quantity token (the part of a quantifier before the quantified
+// expression, e.g. the `{2,5}` in `{2,5}a`) into a `QuantifierKind`
+fn parse_quantifier_kind(pair: Pair<Rule>) -> Result<QuantifierKind> {
+    match pair.as_rule() {
+        Rule::quantifier_range => {
+            let (start, end) = first_last_inner_str(pair)?;
+            Ok(QuantifierKind::Range {
+                start: start.parse().map_err(|_| CompilerError::UnrecognizedSyntax)?,
+                end: end.parse().map_err(|_| CompilerError::UnrecognizedSyntax)?,
+            })
+        }
+        Rule::quantifier_some => Ok(QuantifierKind::Some),
+        Rule::quantifier_any => Ok(QuantifierKind::Any),
+        Rule::quantifier_over => {
+            let amount = first_inner(pair)?.as_str();
+
+            Ok(QuantifierKind::Over(
+                amount.parse().map_err(|_| CompilerError::UnrecognizedSyntax)?,
+            ))
+        }
+        Rule::quantifier_option => Ok(QuantifierKind::Option),
+        Rule::quantifier_amount => {
+            let amount = first_inner(pair)?.as_str();
+
+            Ok(QuantifierKind::Amount(
+                amount.parse().map_err(|_| CompilerError::UnrecognizedSyntax)?,
+            ))
+        }
+        _ => Err(CompilerError::UnrecognizedSyntax.into()),
+    }
+}
+
+fn try_parse_quantifier(
+    pair: Pair<Rule>,
+    env: &mut HashMap<String, AlaniAst>,
+    next_capture_index: &mut u32,
+    named_groups: &mut HashSet<String>,
+    errors: &mut Vec<(CompilerError, Span)>,
 ) -> Result<AlaniAstNode> {
     let quantity = first_inner(pair.clone())?;
-    let kind = first_inner(quantity.clone())?;
-    let expression = create_ast_node(last_inner(pair)?, variables)?;
+    let kind = parse_quantifier_kind(first_inner(quantity.clone())?)?;
+    let expression_node = create_ast_node(
+        last_inner(pair)?,
+        env,
+        next_capture_index,
+        named_groups,
+        errors,
+    );
+
+    // The quantifier's span must cover both its quantity token and its
+    // expression, which pest represents as separate child pairs
+    let span = Span::from(quantity.as_span()).union(expression_node.span);
 
-    let expression = match expression {
-        // AlaniAstNode::Group(group) => Expression::Group(group),
-        AlaniAstNode::Atom(atom) => Expression::Atom(atom),
-        // AlaniAstNode::Range(range) => Expression::Range(range),
-        AlaniAstNode::Symbol(symbol) => Expression::Symbol(symbol),
-        // AlaniAstNode::NegativeCharClass(class) => Expression::NegativeCharClass(class),
+    match &expression_node.node {
+        // Legal quantified expressions. An `Error` expression already failed
+        // to parse and had its own error recorded; don't raise a second one
+        // on top of it
+        AlaniAstNodeKind::Group(_)
+        | AlaniAstNodeKind::Atom(_)
+        | AlaniAstNodeKind::Symbol(_)
+        | AlaniAstNodeKind::CharClass(_)
+        | AlaniAstNodeKind::Error => {}
 
         // unexpected nodes
-        // AlaniAstNode::SpecialSymbol(_) => {
-        //     return Err(CompilerError::UnexpectedSpecialSymbolInQuantifier.into())
-        // }
-        AlaniAstNode::Quantifier(_) => {
+        AlaniAstNodeKind::Quantifier(_) => {
             return Err(CompilerError::UnexpectedQuantifierInQuantifier.into())
         }
-        // AlaniAstNode::Assertion(_) => {
-        //     return Err(CompilerError::UnexpectedAssertionInQuantifier.into())
-        // }
-        // AlaniAstNode::VariableInvocation(_) => {
-        //     return Err(CompilerError::UnexpectedVariableInvocationInQuantifier.into())
-        // }
-        AlaniAstNode::Skip => return Err(CompilerError::UnexpectedSkippedNodeInQuantifier.into()),
-    };
+        AlaniAstNodeKind::Assertion(_) => {
+            return Err(CompilerError::UnexpectedAssertionInQuantifier.into())
+        }
+        AlaniAstNodeKind::VariableInvocation(_) => {
+            return Err(CompilerError::UnexpectedVariableInvocationInQuantifier.into())
+        }
+        AlaniAstNodeKind::Skip => {
+            return Err(CompilerError::UnexpectedSkippedNodeInQuantifier.into())
+        }
+    }
 
     let lazy = quantity.as_str().starts_with(LAZY);
 
+    Ok(AlaniAstNode {
+        node: AlaniAstNodeKind::Quantifier(Quantifier {
+            kind,
+            lazy,
+            expression: Box::new(expression_node),
+        }),
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn char_range_rejects_inverted_bounds() {
+        let error = char_range('z', 'a').unwrap_err();
+
+        assert!(matches!(
+            error.downcast::<CompilerError>().unwrap(),
+            CompilerError::InvertedCharRange
+        ));
+    }
+
+    #[test]
+    fn char_range_allows_ascending_bounds() {
+        let item = char_range('a', 'z').unwrap();
+
+        assert!(matches!(
+            item,
+            ClassItem::Range {
+                start: 'a',
+                end: 'z'
+            }
+        ));
+    }
+
+    #[test]
+    fn check_not_already_declared_rejects_redeclaration() {
+        let mut env = HashMap::new();
+        env.insert("greeting".to_owned(), AlaniAst::Empty);
+
+        let error = check_not_already_declared(&env, "greeting").unwrap_err();
+
+        assert!(matches!(
+            error.downcast::<CompilerError>().unwrap(),
+            CompilerError::VariableAlreadyDeclared
+        ));
+    }
+
+    #[test]
+    fn resolve_variable_body_rejects_undefined_name() {
+        let env = HashMap::new();
 
+        let error = resolve_variable_body(&env, "greeting").unwrap_err();
+
+        assert!(matches!(
+            error.downcast::<CompilerError>().unwrap(),
+            CompilerError::UndefinedVariable
+        ));
+    }
+
+    // A declaration's own name is only inserted into `env` once its body has
+    // finished parsing (see `parse_variable_declaration`), so a variable that
+    // invokes itself resolves the same way an undefined variable would
+    #[test]
+    fn resolve_variable_body_rejects_self_reference() {
+        let env = HashMap::new();
+
+        let error = resolve_variable_body(&env, "self_referential").unwrap_err();
+
+        assert!(matches!(
+            error.downcast::<CompilerError>().unwrap(),
+            CompilerError::UndefinedVariable
+        ));
+    }
+
+    #[test]
+    fn renumber_groups_rejects_duplicate_named_group_across_splice() {
+        let duplicate = AlaniAstNode {
+            node: AlaniAstNodeKind::Group(Group {
+                kind: GroupKind::Named {
+                    index: 0,
+                    name: "x".to_owned(),
+                },
+                body: Vec::new(),
+            }),
+            span: Span { start: 0, end: 1 },
+        };
+
+        let mut nodes = vec![duplicate];
+        let mut next_capture_index = 1;
+        let mut named_groups = HashSet::from(["x".to_owned()]);
+        let mut errors = Vec::new();
+
+        renumber_groups(&mut nodes, &mut next_capture_index, &mut named_groups, &mut errors);
+
+        assert!(matches!(
+            errors.as_slice(),
+            [(CompilerError::DuplicateGroupName, _)]
+        ));
+        assert!(matches!(nodes[0].node, AlaniAstNodeKind::Error));
+    }
+
+    #[test]
+    fn assertion_kind_negates_lookaround_independent_of_marker() {
+        let lookahead = assertion_kind(Rule::lookahead_marker, true, Vec::new()).unwrap();
+        assert!(matches!(
+            lookahead,
+            AssertionKind::Lookahead { negated: true, .. }
+        ));
+
+        let lookbehind = assertion_kind(Rule::lookbehind_marker, false, Vec::new()).unwrap();
+        assert!(matches!(
+            lookbehind,
+            AssertionKind::Lookbehind {
+                negated: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn assertion_kind_negates_word_boundary() {
+        let negated = assertion_kind(Rule::word_boundary_marker, true, Vec::new()).unwrap();
+        assert!(matches!(negated, AssertionKind::NotWordBoundary));
+
+        let plain = assertion_kind(Rule::word_boundary_marker, false, Vec::new()).unwrap();
+        assert!(matches!(plain, AssertionKind::WordBoundary));
+    }
 }
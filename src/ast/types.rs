@@ -12,17 +12,16 @@ pub enum QuantifierKind {
     Amount(u32),
 }
 
+// `expression` is the full node produced for the quantified expression
+// (restricted to `Atom`/`Symbol`/`Group`/`CharClass`/`Error` by
+// `try_parse_quantifier`), span and all, rather than just its inner value,
+// so a quantified group's `GroupKind` and a quantified node's position are
+// still visible to anything walking the tree via `Visitor`
 #[derive(Debug, Clone)]
 pub struct Quantifier {
     pub kind: QuantifierKind,
     pub lazy: bool,
-    pub expression: Box<Expression>,
-}
-
-#[derive(Debug, Clone)]
-pub enum Expression {
-    Atom(String),
-    Symbol(Symbol)
+    pub expression: Box<AlaniAstNode>,
 }
 
 // ========================
@@ -54,16 +53,116 @@ pub enum SymbolKind {
     Boundary,
 }
 
+// ========================
+// ======== GROUPS ========
+// ========================
+
+#[derive(Debug, Clone)]
+pub enum GroupKind {
+    NonCapturing,
+    Capturing { index: u32 },
+    Named { index: u32, name: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub kind: GroupKind,
+    pub body: Vec<AlaniAstNode>,
+}
+
+// ========================
+// ===== CHAR CLASSES =====
+// ========================
+
+#[derive(Debug, Clone)]
+pub enum ClassItem {
+    Char(char),
+    Range { start: char, end: char },
+}
+
+#[derive(Debug, Clone)]
+pub struct CharClass {
+    pub items: Vec<ClassItem>,
+    pub negated: bool,
+}
+
+// ========================
+// ====== ASSERTIONS ======
+// ========================
+
+#[derive(Debug, Clone)]
+pub enum AssertionKind {
+    StartText,
+    EndText,
+    WordBoundary,
+    NotWordBoundary,
+    Lookahead {
+        negated: bool,
+        body: Vec<AlaniAstNode>,
+    },
+    Lookbehind {
+        negated: bool,
+        body: Vec<AlaniAstNode>,
+    },
+}
+
+// ========================
+// ======== SPANS =========
+// ========================
+
+// A byte-offset range into the original source, so a `CompilerError` can
+// point back at the text that produced it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    // Merges two spans into the smallest span covering both, e.g. when a
+    // quantifier's span must cover both its quantity token and its expression
+    pub fn union(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+impl From<pest::Span<'_>> for Span {
+    fn from(span: pest::Span<'_>) -> Self {
+        Span {
+            start: span.start(),
+            end: span.end(),
+        }
+    }
+}
+
 // ========================
 // ========= AST ==========
 // ========================
 
 #[derive(Debug, Clone)]
-pub enum AlaniAstNode {
+pub enum AlaniAstNodeKind {
     Quantifier(Quantifier),
     Atom(String),
     Symbol(Symbol),
+    Group(Group),
+    Assertion(AssertionKind),
+    CharClass(CharClass),
+    VariableInvocation(Vec<AlaniAstNode>),
     Skip,
+    // Placeholder for a node that failed to parse. Produced by
+    // `create_ast_node` in place of bailing out, so the surrounding tree
+    // (siblings, ancestors) still parses and the caller gets a usable
+    // partial AST alongside the collected diagnostics
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct AlaniAstNode {
+    pub node: AlaniAstNodeKind,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
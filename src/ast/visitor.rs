@@ -0,0 +1,59 @@
+use super::types::{AlaniAst, AlaniAstNode, AlaniAstNodeKind, AssertionKind};
+
+// Implementors get a pre-order and post-order callback for every node
+// `visit` walks over; both default to doing nothing, so a consumer only
+// overrides the hooks it cares about (e.g. a unique-named-group validation
+// pass only needs `visit_pre`)
+pub trait Visitor {
+    fn visit_pre(&mut self, _node: &AlaniAstNode) {}
+    fn visit_post(&mut self, _node: &AlaniAstNode) {}
+}
+
+// A single entry in the explicit work-list: either "enter this node" (which
+// may push its children, plus a matching `Leave` so the post-order hook
+// still fires once they're done) or "leave this node"
+enum Work<'a> {
+    Enter(&'a AlaniAstNode),
+    Leave(&'a AlaniAstNode),
+}
+
+// Walks `ast`, calling `visitor`'s pre/post hooks for every node, using an
+// explicit heap-allocated stack rather than call recursion so a pathologically
+// deep tree (e.g. many nested groups) can't blow the call stack
+pub fn visit(ast: &AlaniAst, visitor: &mut impl Visitor) {
+    let root = match ast {
+        AlaniAst::Root(nodes) => nodes,
+        AlaniAst::Empty => return,
+    };
+
+    let mut stack: Vec<Work> = root.iter().rev().map(Work::Enter).collect();
+
+    while let Some(work) = stack.pop() {
+        match work {
+            Work::Enter(node) => {
+                visitor.visit_pre(node);
+                stack.push(Work::Leave(node));
+                stack.extend(children_of(node).iter().rev().map(|child| Work::Enter(child)));
+            }
+            Work::Leave(node) => visitor.visit_post(node),
+        }
+    }
+}
+
+// Returns the direct child nodes of `node`, i.e. the nodes a recursive walk
+// would have descended into
+fn children_of(node: &AlaniAstNode) -> &[AlaniAstNode] {
+    match &node.node {
+        AlaniAstNodeKind::Group(group) => &group.body,
+        AlaniAstNodeKind::Assertion(AssertionKind::Lookahead { body, .. }) => body,
+        AlaniAstNodeKind::Assertion(AssertionKind::Lookbehind { body, .. }) => body,
+        AlaniAstNodeKind::VariableInvocation(body) => body,
+        // The quantified expression is itself a full node (see `Quantifier`'s
+        // doc comment), so it's visited like any other child rather than
+        // reaching past it into a `Group`'s body
+        AlaniAstNodeKind::Quantifier(quantifier) => {
+            std::slice::from_ref(quantifier.expression.as_ref())
+        }
+        _ => &[],
+    }
+}